@@ -0,0 +1,114 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::AnyPool;
+
+use crate::{
+    error::Error,
+    session::{session_cookie, AuthUser},
+    todo::{CreateTodo, Todo, TodoFilter, UpdateTodo},
+    user::User,
+};
+
+#[derive(Serialize)]
+pub struct TodoPage {
+    items: Vec<Todo>,
+    limit: i64,
+    offset: i64,
+    total: i64,
+}
+
+pub async fn list_todos(
+    State(dbpool): State<AnyPool>,
+    AuthUser(owner_id): AuthUser,
+    Query(filter): Query<TodoFilter>,
+) -> Result<Json<TodoPage>, Error> {
+    filter.validate()?;
+
+    let limit = filter.limit();
+    let offset = filter.offset();
+
+    let total = Todo::count(dbpool.clone(), owner_id, filter.completed()).await?;
+    let items = Todo::list(dbpool, owner_id, filter).await?;
+
+    Ok(Json(TodoPage {
+        items,
+        limit,
+        offset,
+        total,
+    }))
+}
+
+pub async fn recent_todos(
+    State(dbpool): State<AnyPool>,
+    AuthUser(owner_id): AuthUser,
+    Path(n): Path<i64>,
+) -> Result<Json<Vec<Todo>>, Error> {
+    Todo::recent(dbpool, owner_id, n).await.map(Json)
+}
+
+pub async fn read_todo(
+    State(dbpool): State<AnyPool>,
+    AuthUser(owner_id): AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Json<Todo>, Error> {
+    Todo::read(dbpool, owner_id, id).await.map(Json)
+}
+
+pub async fn create_todo(
+    State(dbpool): State<AnyPool>,
+    AuthUser(owner_id): AuthUser,
+    Json(new_todo): Json<CreateTodo>,
+) -> Result<Json<Todo>, Error> {
+    Todo::create(dbpool, owner_id, new_todo).await.map(Json)
+}
+
+pub async fn update_todo(
+    State(dbpool): State<AnyPool>,
+    AuthUser(owner_id): AuthUser,
+    Path(id): Path<i64>,
+    Json(updated_todo): Json<UpdateTodo>,
+) -> Result<Json<Todo>, Error> {
+    Todo::update(dbpool, owner_id, id, updated_todo)
+        .await
+        .map(Json)
+}
+
+pub async fn delete_todo(
+    State(dbpool): State<AnyPool>,
+    AuthUser(owner_id): AuthUser,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, Error> {
+    Todo::delete(dbpool, owner_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+pub async fn signup(
+    State(dbpool): State<AnyPool>,
+    Json(credentials): Json<Credentials>,
+) -> Result<Response, Error> {
+    let user = User::signup(dbpool, &credentials.username, &credentials.password).await?;
+    Ok(with_session_cookie(user))
+}
+
+pub async fn login(
+    State(dbpool): State<AnyPool>,
+    Json(credentials): Json<Credentials>,
+) -> Result<Response, Error> {
+    let user = User::login(dbpool, &credentials.username, &credentials.password).await?;
+    Ok(with_session_cookie(user))
+}
+
+fn with_session_cookie(user: User) -> Response {
+    ([(header::SET_COOKIE, session_cookie(user.id()))], Json(user)).into_response()
+}
@@ -0,0 +1,95 @@
+// Dispatches between the SQLite and Postgres backends that can sit behind
+// `DATABASE_URL`, since `sqlx::Any` erases the concrete database but the two
+// still disagree on bind-placeholder syntax and migration directories.
+
+use sqlx::any::AnyKind;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    pub fn from_url(url: &str) -> Self {
+        if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+            Backend::Postgres
+        } else {
+            Backend::Sqlite
+        }
+    }
+}
+
+impl From<AnyKind> for Backend {
+    fn from(kind: AnyKind) -> Self {
+        match kind {
+            AnyKind::Postgres => Backend::Postgres,
+            AnyKind::Sqlite => Backend::Sqlite,
+        }
+    }
+}
+
+/// Rewrites the portable `?` bind placeholders used throughout this crate's
+/// SQL into the syntax `backend` understands: SQLite already speaks `?`,
+/// Postgres needs positional `$1`, `$2`, ...
+pub fn placeholders(sql: &str, backend: Backend) -> String {
+    match backend {
+        Backend::Sqlite => sql.to_string(),
+        Backend::Postgres => {
+            let mut rewritten = String::with_capacity(sql.len());
+            let mut n = 0;
+            for c in sql.chars() {
+                if c == '?' {
+                    n += 1;
+                    rewritten.push('$');
+                    rewritten.push_str(&n.to_string());
+                } else {
+                    rewritten.push(c);
+                }
+            }
+            rewritten
+        }
+    }
+}
+
+/// Rewrites `sql` for whichever backend is behind `dbpool`.
+pub fn rewrite(dbpool: &sqlx::AnyPool, sql: &str) -> String {
+    placeholders(sql, dbpool.any_kind().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPDATE_TODO_SQL: &str = "update todos set body = ?, completed = ?, \
+                updated_at = current_timestamp where id = ? and owner_id = ? returning * ";
+
+    #[test]
+    fn sqlite_passes_placeholders_through_unchanged() {
+        assert_eq!(
+            placeholders(UPDATE_TODO_SQL, Backend::Sqlite),
+            UPDATE_TODO_SQL
+        );
+    }
+
+    #[test]
+    fn postgres_numbers_placeholders_in_order() {
+        assert_eq!(
+            placeholders(UPDATE_TODO_SQL, Backend::Postgres),
+            "update todos set body = $1, completed = $2, \
+                updated_at = current_timestamp where id = $3 and owner_id = $4 returning * "
+        );
+    }
+
+    #[test]
+    fn from_url_recognizes_postgres_schemes() {
+        assert!(Backend::from_url("postgres://user@host/db") == Backend::Postgres);
+        assert!(Backend::from_url("postgresql://user@host/db") == Backend::Postgres);
+    }
+
+    #[test]
+    fn from_url_defaults_to_sqlite() {
+        assert!(Backend::from_url("sqlite:db.sqlite") == Backend::Sqlite);
+        assert!(Backend::from_url("db.sqlite") == Backend::Sqlite);
+    }
+}
@@ -0,0 +1,45 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+// The single error type the rest of the crate converts into, so that handlers
+// can just bubble up `?` and have axum turn it into the right HTTP response.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unauthorized")]
+    Unauthorized,
+
+    #[error("username already taken")]
+    UsernameTaken,
+
+    #[error("bad request: {0}")]
+    BadRequest(String),
+
+    #[error("request timed out")]
+    Timeout,
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error(transparent)]
+    Bcrypt(#[from] bcrypt::BcryptError),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::UsernameTaken => StatusCode::CONFLICT,
+            Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Error::Sqlx(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND,
+            Error::Sqlx(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Bcrypt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
@@ -0,0 +1,36 @@
+// Liveness/readiness probes so an orchestrator can tell "process alive"
+// apart from "database reachable".
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use sqlx::AnyPool;
+
+/// Always `200 OK` — the process is up and serving requests.
+pub async fn liveness() -> &'static str {
+    "OK"
+}
+
+/// `200 OK` with pool stats if a connection can be acquired and `select 1`
+/// succeeds, `503` otherwise.
+pub async fn readiness(State(dbpool): State<AnyPool>) -> impl IntoResponse {
+    match check(&dbpool).await {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(json!({
+                "db": "up",
+                "connections_idle": dbpool.num_idle(),
+                "connections_size": dbpool.size(),
+            })),
+        ),
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "db": "down" })),
+        ),
+    }
+}
+
+async fn check(dbpool: &AnyPool) -> Result<(), sqlx::Error> {
+    let mut conn = dbpool.acquire().await?;
+    sqlx::query("select 1").execute(&mut *conn).await?;
+    Ok(())
+}
@@ -1,28 +1,72 @@
 use clap::{Parser, Subcommand};
 use colored_json::prelude::*;
-use hyper::{body::HttpBody as _, header::CONTENT_TYPE, Body, Client, Method, Request, Uri};
+use cookie::Cookie;
+use hyper::{
+    body::HttpBody as _,
+    header::{CONTENT_TYPE, COOKIE, SET_COOKIE},
+    Body, Client, Method, Request, Uri,
+};
 use router::create_router;
 use serde_json::json;
 use yansi::Paint;
 
 mod api;
+mod db;
 mod error;
+mod health;
 mod router;
+mod session;
 mod todo;
+mod user;
 
 #[derive(Parser)]
 struct Cli {
-    /// Base URL of API service
-    url: hyper::Uri,
-
     #[command(subcommand)]
-    command: Commands,
+    mode: Mode,
+}
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Start the HTTP API server
+    Serve,
+    /// Issue a request to a running API server
+    Call {
+        /// Base URL of API service
+        url: hyper::Uri,
+
+        #[command(subcommand)]
+        command: Commands,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    /// Create an account and store its session cookie for later commands
+    Signup {
+        /// The account username
+        username: String,
+        /// The account password
+        password: String,
+    },
+    /// Log in and store the session cookie for later commands
+    Login {
+        /// The account username
+        username: String,
+        /// The account password
+        password: String,
+    },
     /// List all todos
-    List,
+    List {
+        /// Maximum number of todos to return
+        #[arg(long)]
+        limit: Option<i64>,
+        /// Number of todos to skip
+        #[arg(long)]
+        offset: Option<i64>,
+        /// Only show todos with this completed status
+        #[arg(long)]
+        completed: Option<bool>,
+    },
     /// Create a new todo
     Create {
         /// The todo body
@@ -50,26 +94,63 @@ enum Commands {
     },
 }
 
+// Path of the file the CLI stashes the session cookie in between
+// invocations, since each `api-server call ...` is a fresh process.
+fn session_cookie_file() -> std::path::PathBuf {
+    if let Ok(path) = std::env::var("API_SERVER_COOKIE_FILE") {
+        return std::path::PathBuf::from(path);
+    }
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    std::path::PathBuf::from(home).join(".api-server-session")
+}
+
+fn load_session_cookie() -> Option<String> {
+    std::fs::read_to_string(session_cookie_file())
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn save_session_cookie(cookie: &str) -> std::io::Result<()> {
+    std::fs::write(session_cookie_file(), cookie)
+}
+
+// Issues one HTTP request, sending `cookie` (if any) and returning the
+// `Set-Cookie` value (if any) as a bare `name=value` pair the caller can
+// stash and replay on a later request.
 async fn request(
     url: hyper::Uri,
     method: Method,
     body: Option<String>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    cookie: Option<&str>,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
     let client = Client::new();
 
+    let mut builder = Request::builder()
+        .uri(url)
+        .method(method)
+        .header("Content-Type", "application/json");
+
+    if let Some(cookie) = cookie {
+        builder = builder.header(COOKIE, cookie);
+    }
+
     let mut res = client
         .request(
-            Request::builder()
-                .uri(url)
-                .method(method)
-                .header("Content-Type", "application/json")
-                .body(
-                    body.map(|s| Body::from(s))
-                        .unwrap_or_else(|| Body::empty()),
-                )?,
+            builder.body(
+                body.map(|s| Body::from(s))
+                    .unwrap_or_else(|| Body::empty()),
+            )?,
         )
         .await?;
 
+    let session_cookie = res
+        .headers()
+        .get(SET_COOKIE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|raw| Cookie::parse(raw.to_owned()).ok())
+        .map(|cookie| format!("{}={}", cookie.name(), cookie.value()));
+
     let mut buf = Vec::new();
     while let Some(next) = res.data().await {
         let chunk = next?;
@@ -90,7 +171,7 @@ async fn request(
         println!("{}", &s);
     }
 
-    Ok(())
+    Ok(session_cookie)
 }
 // async fn request(
 //     url: hyper::Uri,
@@ -139,59 +220,138 @@ async fn request(
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let cli = Cli::parse();
+    match Cli::parse().mode {
+        Mode::Serve => serve().await,
+        Mode::Call { url, command } => call(url, command).await,
+    }
+}
 
+// Issues a single HTTP request against a running server for one CLI
+// subcommand, then exits. This is a separate path from `serve()` below so a
+// client invocation can never fall through into starting a server.
+async fn call(
+    url: hyper::Uri,
+    command: Commands,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let mut uri_builder = Uri::builder();
 
-    if let Some(scheme) = cli.url.scheme() {
+    if let Some(scheme) = url.scheme() {
         uri_builder = uri_builder.scheme(scheme.clone());
     }
 
-    if let Some(authority) = cli.url.authority() {
-        uri_builder = uri_builder.authority(authority.cloen());
+    if let Some(authority) = url.authority() {
+        uri_builder = uri_builder.authority(authority.clone());
     }
 
-    match cli.command {
-        Commands::List => {
+    match command {
+        Commands::Signup { username, password } => {
+            let set_cookie = request(
+                uri_builder.path_and_query("/v1/signup").build()?,
+                Method::POST,
+                Some(json!({ "username": username, "password": password }).to_string()),
+                None,
+            )
+            .await?;
+            store_session_cookie(set_cookie)
+        }
+        Commands::Login { username, password } => {
+            let set_cookie = request(
+                uri_builder.path_and_query("/v1/login").build()?,
+                Method::POST,
+                Some(json!({ "username": username, "password": password }).to_string()),
+                None,
+            )
+            .await?;
+            store_session_cookie(set_cookie)
+        }
+        Commands::List {
+            limit,
+            offset,
+            completed,
+        } => {
+            let mut params = Vec::new();
+            if let Some(limit) = limit {
+                params.push(format!("limit={}", limit));
+            }
+            if let Some(offset) = offset {
+                params.push(format!("offset={}", offset));
+            }
+            if let Some(completed) = completed {
+                params.push(format!("completed={}", completed));
+            }
+
+            let path = if params.is_empty() {
+                "/v1/todos".to_string()
+            } else {
+                format!("/v1/todos?{}", params.join("&"))
+            };
+
             request(
-                uri_builder.path_and_query("/v1/todos").build()?;
+                uri_builder.path_and_query(path).build()?,
+                Method::GET,
+                None,
+                load_session_cookie().as_deref(),
             )
-            .await
+            .await?;
+            Ok(())
         }
         Commands::Delete { id } => {
             request(
-                uri_builder.path_and_query(foamat!("/v1/todos/{}", id))
+                uri_builder.path_and_query(format!("/v1/todos/{}", id))
                 .build()?,
                 Method::DELETE,
                 None,
+                load_session_cookie().as_deref(),
             )
-            .await
+            .await?;
+            Ok(())
         }
         Commands::Read { id } => {
             request(
                 uri_builder.path_and_query(format!("/v1/todos/{}", id)).build()?,
                 Method::GET,
                 None,
+                load_session_cookie().as_deref(),
             )
-            .await
+            .await?;
+            Ok(())
         }
         Commands::Create { body } => {
             request(
                 uri_builder.path_and_query("/v1/todos").build()?,
                 Method::POST,
                 Some(json!({ "body": body }).to_string()),
+                load_session_cookie().as_deref(),
             )
-            .await
+            .await?;
+            Ok(())
         }
         Commands::Update { id, body, completed } => {
             request(
             uri_builder.path_and_query(format!("/v1/todos/{}", id)).build()?,
             Method::PUT,
             Some(json!({"body":body,"completed":completed}).to_string()),
+            load_session_cookie().as_deref(),
         )
-        .await
+        .await?;
+        Ok(())
         }
     }
+}
+
+// Persists the session cookie minted by a signup/login response so later
+// `call` invocations (separate processes) can replay it.
+fn store_session_cookie(
+    set_cookie: Option<String>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let set_cookie = set_cookie.ok_or("server did not return a session cookie")?;
+    save_session_cookie(&set_cookie)?;
+    Ok(())
+}
+
+// Boots the HTTP API server and runs until a shutdown signal is received,
+// draining in-flight requests before the process exits.
+async fn serve() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Initializes the tracing and logging for our service and its dependencies.
     init_tracing();
 
@@ -199,17 +359,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let dbpool = init_dbpool().await.expect("couldn't initialize db pool");
 
     // Creates the core application service and its routes.
-    let router = create_router(dbpool).await;
+    let router = create_router(dbpool.clone()).await;
 
     // Fetches the binding address from the environment variable BIND_ADDR, or uses the default value of 127.0.0.1:3000.
     let bind_addr = std::env::var("BIND_ADDR").unwrap_or_else(|_| "127.0.0.1:3000".to_string());
 
     // Parses the binding address into a socket address.
-    // Creates the service and starts the HTTP server.
+    // Creates the service and starts the HTTP server, draining in-flight requests on SIGINT/SIGTERM.
     axum::Server::bind(&bind_addr.parse().unwrap())
         .serve(router.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
         .await
-        .expect("unable to start server")
+        .expect("unable to start server");
+
+    dbpool.close().await;
+
+    Ok(())
+}
+
+// Resolves once either Ctrl-C or (on Unix) SIGTERM is received, so the
+// server can stop accepting new connections and let in-flight ones drain.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install SIGINT handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("shutdown signal received, draining in-flight requests");
 }
 
 fn init_tracing() {
@@ -233,28 +426,84 @@ fn init_tracing() {
         .init();
 }
 
-async fn init_dbpool() -> Result<sqlx::Pool<sqlx::Sqlite>, sqlx::Error> {
-    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+async fn init_dbpool() -> Result<sqlx::AnyPool, sqlx::Error> {
+    use sqlx::any::AnyPoolOptions;
+    use sqlx::sqlite::SqliteConnectOptions;
     use std::str::FromStr;
 
+    use db::Backend;
+
     // We’ll try to read the DATABASE_URL environment variable, or default to sqlite:db.sqlite
     // if not defined (which opens a file called db.sqlite in the current working directory)
 
+    sqlx::any::install_default_drivers();
+
     let db_connection_str =
         std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:db.sqlite".to_string());
-
-    // When we connect to the database, we ask the driver to create the database if it doesn’t already exist.
-    let dbpool = SqlitePoolOptions::new()
-        .connect_with(SqliteConnectOptions::from_str(&db_connection_str)?.create_if_missing(true))
-        .await
-        .expect("can't connect to database");
-
-    // After we’ve connected to the DB, we run any migrations that are needed.
-    // We can pass our newly created DB pool directly to SQLx, which will obtain a connection from the pool.
-    sqlx::migrate!()
-        .run(&dbpool)
-        .await
-        .expect("database migration failed.");
+    let backend = Backend::from_url(&db_connection_str);
+    let pool_options = pool_options();
+
+    // SQLite is opened from a local file and created on first run; Postgres
+    // just connects to whatever server is listening at the URL.
+    let dbpool = match backend {
+        Backend::Sqlite => {
+            let options =
+                SqliteConnectOptions::from_str(&db_connection_str)?.create_if_missing(true);
+            pool_options
+                .connect_with(options.into())
+                .await
+                .expect("can't connect to database")
+        }
+        Backend::Postgres => pool_options
+            .connect(&db_connection_str)
+            .await
+            .expect("can't connect to database"),
+    };
+
+    // After we’ve connected to the DB, we run any migrations that are needed,
+    // picking the migration directory that matches the backend.
+    match backend {
+        Backend::Sqlite => sqlx::migrate!("migrations/sqlite").run(&dbpool).await,
+        Backend::Postgres => sqlx::migrate!("migrations/postgres").run(&dbpool).await,
+    }
+    .expect("database migration failed.");
 
     Ok(dbpool)
 }
+
+// Builds the pool sizing knobs from the environment, falling back to a
+// CPU-derived default for max_connections so we don't have to guess a fixed
+// number that's wrong for every deployment.
+fn pool_options() -> sqlx::any::AnyPoolOptions {
+    use sqlx::any::AnyPoolOptions;
+
+    let default_max_connections = std::cmp::max(4, num_cpus::get() as u32 * 2);
+
+    let max_connections = std::env::var("DB_MAX_CONNECTIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default_max_connections);
+
+    let min_connections = std::env::var("DB_MIN_CONNECTIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+
+    let acquire_timeout = std::env::var("DB_ACQUIRE_TIMEOUT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(std::time::Duration::from_secs(30));
+
+    tracing::info!(
+        max_connections,
+        min_connections,
+        acquire_timeout_secs = acquire_timeout.as_secs(),
+        "configuring database connection pool",
+    );
+
+    AnyPoolOptions::new()
+        .max_connections(max_connections)
+        .min_connections(min_connections)
+        .acquire_timeout(acquire_timeout)
+}
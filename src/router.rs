@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use axum::{
+    error_handling::HandleErrorLayer,
+    routing::{get, post},
+    BoxError, Router,
+};
+use sqlx::AnyPool;
+use tower::{timeout::TimeoutLayer, ServiceBuilder};
+
+use crate::{api, error::Error, health};
+
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+pub async fn create_router(dbpool: AnyPool) -> Router {
+    Router::new()
+        .route("/v1/todos", get(api::list_todos).post(api::create_todo))
+        .route(
+            "/v1/todos/:id",
+            get(api::read_todo)
+                .put(api::update_todo)
+                .delete(api::delete_todo),
+        )
+        .route("/v1/todos/last/:n", get(api::recent_todos))
+        .route("/v1/health", get(health::liveness))
+        .route("/v1/ready", get(health::readiness))
+        .route("/v1/signup", post(api::signup))
+        .route("/v1/login", post(api::login))
+        .with_state(dbpool)
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(handle_timeout_error))
+                .layer(TimeoutLayer::new(request_timeout())),
+        )
+}
+
+fn request_timeout() -> Duration {
+    let secs = std::env::var("REQUEST_TIMEOUT")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS);
+
+    Duration::from_secs(secs)
+}
+
+// TimeoutLayer surfaces an elapsed deadline as a boxed tower error rather
+// than our own Error type, so HandleErrorLayer translates it into a 504.
+async fn handle_timeout_error(_err: BoxError) -> Error {
+    Error::Timeout
+}
@@ -0,0 +1,78 @@
+// Issues and verifies the signed session cookie used to carry an
+// authenticated user's id between requests.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue},
+};
+use cookie::{Cookie, CookieJar, Key, SameSite};
+use once_cell::sync::Lazy;
+use sqlx::AnyPool;
+
+use crate::error::Error;
+
+pub const SESSION_COOKIE: &str = "session";
+
+// A randomly generated key would invalidate every session on restart and
+// would differ between replicas behind a load balancer, so there is no
+// usable fallback here: require an explicit, stable secret.
+static SESSION_KEY: Lazy<Key> = Lazy::new(|| {
+    let secret = std::env::var("SESSION_SECRET")
+        .expect("SESSION_SECRET must be set to a stable secret of at least 32 bytes");
+    Key::derive_from(secret.as_bytes())
+});
+
+/// Builds a signed `Set-Cookie` header value carrying `user_id`.
+pub fn session_cookie(user_id: i64) -> HeaderValue {
+    let cookie = Cookie::build(SESSION_COOKIE, user_id.to_string())
+        .http_only(true)
+        .secure(secure_cookies())
+        .same_site(SameSite::Lax)
+        .path("/")
+        .finish();
+
+    let mut jar = CookieJar::new();
+    jar.signed_mut(&SESSION_KEY).add(cookie);
+
+    HeaderValue::from_str(&jar.get(SESSION_COOKIE).unwrap().to_string()).unwrap()
+}
+
+// Secure by default, since the session cookie should never travel over
+// plain HTTP in production; set SESSION_COOKIE_SECURE=false to serve it
+// over the plain-HTTP dev setup (e.g. BIND_ADDR=127.0.0.1:3000).
+fn secure_cookies() -> bool {
+    std::env::var("SESSION_COOKIE_SECURE")
+        .map(|v| v != "false" && v != "0")
+        .unwrap_or(true)
+}
+
+/// Extractor that pulls the authenticated user's id out of the signed
+/// session cookie, rejecting the request with `Error::Unauthorized` if it is
+/// missing or has been tampered with.
+pub struct AuthUser(pub i64);
+
+#[async_trait]
+impl FromRequestParts<AnyPool> for AuthUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &AnyPool,
+    ) -> Result<Self, Self::Rejection> {
+        let mut jar = CookieJar::new();
+        for value in parts.headers.get_all(header::COOKIE) {
+            if let Ok(raw) = value.to_str() {
+                for cookie in Cookie::split_parse(raw.to_owned()).flatten() {
+                    jar.add_original(cookie);
+                }
+            }
+        }
+
+        jar.signed(&SESSION_KEY)
+            .get(SESSION_COOKIE)
+            .and_then(|cookie| cookie.value().parse().ok())
+            .map(AuthUser)
+            .ok_or(Error::Unauthorized)
+    }
+}
@@ -1,8 +1,17 @@
 // We’re deriving the Serialize trait from the serde crate, and sqlx::FromRow which allows us to get a Todo from a SQLx query.
 
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{query, query_as, query_scalar, AnyPool};
+
+use crate::{db, error::Error};
+
+const DEFAULT_LIMIT: i64 = 50;
+
 #[derive(Serialize, Clone, sqlx::FromRow)]
 pub struct Todo {
     id: i64,
+    owner_id: i64,
     body: String,
     completed: bool,
     created_at: NaiveDateTime, // We use the chrono::NaiveDateTime type to map SQL timestamps into Rust objects.
@@ -10,25 +19,80 @@ pub struct Todo {
 }
 
 impl Todo {
-    pub async fn list(dbpool: SqlitePool) -> Result<Vec<Todo>, Error> {
-        // Selects all todos from the todos table.
-        query_as("select * from todos")
+    pub async fn list(dbpool: AnyPool, owner_id: i64, filter: TodoFilter) -> Result<Vec<Todo>, Error> {
+        // Selects todos belonging to owner_id, optionally filtered by completed status and paginated.
+        let mut sql = String::from("select * from todos where owner_id = ?");
+        if filter.completed.is_some() {
+            sql.push_str(" and completed = ?");
+        }
+        sql.push_str(" order by created_at desc limit ? offset ?");
+        let sql = db::rewrite(&dbpool, &sql);
+
+        let mut query = query_as(&sql).bind(owner_id);
+        if let Some(completed) = filter.completed {
+            query = query.bind(completed);
+        }
+        query
+            .bind(filter.limit())
+            .bind(filter.offset())
             .fetch_all(&dbpool)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn read(dbpool: SqlitePool, id: i64) -> Result<Todo, Error> {
-        // Selects one todo from the todos table with matching id field.
-        query_as("select * from todos where id = ?")
+    pub async fn count(dbpool: AnyPool, owner_id: i64, completed: Option<bool>) -> Result<i64, Error> {
+        // Counts todos belonging to owner_id, optionally filtered by completed status, for pagination envelopes.
+        let mut sql = String::from("select count(*) from todos where owner_id = ?");
+        if completed.is_some() {
+            sql.push_str(" and completed = ?");
+        }
+        let sql = db::rewrite(&dbpool, &sql);
+
+        let mut query = query_scalar(&sql).bind(owner_id);
+        if let Some(completed) = completed {
+            query = query.bind(completed);
+        }
+        query.fetch_one(&dbpool).await.map_err(Into::into)
+    }
+
+    pub async fn recent(dbpool: AnyPool, owner_id: i64, n: i64) -> Result<Vec<Todo>, Error> {
+        // Selects the n most recently created todos belonging to owner_id.
+        // A negative n means "no limit" on SQLite but errors on Postgres, so
+        // clamp it to non-negative before binding.
+        let sql = db::rewrite(
+            &dbpool,
+            "select * from todos where owner_id = ? order by created_at desc limit ?",
+        );
+        query_as(&sql)
+            .bind(owner_id)
+            .bind(n.max(0))
+            .fetch_all(&dbpool)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn read(dbpool: AnyPool, owner_id: i64, id: i64) -> Result<Todo, Error> {
+        // Selects one todo from the todos table with matching id field, scoped to its owner.
+        let sql = db::rewrite(&dbpool, "select * from todos where id = ? and owner_id = ?");
+        query_as(&sql)
             .bind(id)
+            .bind(owner_id)
             .fetch_one(&dbpool)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn create(dbpool: SqlitePool, new_todo: CreateTodo) -> Result<Todo, Error> {
-        query_as("insert into todos (body) values (?) returning *")
+    pub async fn create(
+        dbpool: AnyPool,
+        owner_id: i64,
+        new_todo: CreateTodo,
+    ) -> Result<Todo, Error> {
+        let sql = db::rewrite(
+            &dbpool,
+            "insert into todos (owner_id, body) values (?, ?) returning *",
+        );
+        query_as(&sql)
+            .bind(owner_id)
             .bind(new_todo.body())
             .fetch_one(&dbpool)
             .await
@@ -36,28 +100,68 @@ impl Todo {
     }
 
     pub async fn update(
-        dbpool: SqlitePool,
+        dbpool: AnyPool,
+        owner_id: i64,
         id: i64,
         updated_todo: UpdateTodo,
     ) -> Result<Todo, Error> {
-        query_as(
+        let sql = db::rewrite(
+            &dbpool,
             "update todos set body = ?, completed = ?, \
-                updated_at = datetime('now') where id = ? returning * ",
-        )
-        .bind(updated_todo.body())
-        .bind(updated_todo.completed())
-        .bind(id)
-        .fetch_one(&dbpool)
-        .await
-        .map_err(Into::into)
+                updated_at = current_timestamp where id = ? and owner_id = ? returning * ",
+        );
+        query_as(&sql)
+            .bind(updated_todo.body())
+            .bind(updated_todo.completed())
+            .bind(id)
+            .bind(owner_id)
+            .fetch_one(&dbpool)
+            .await
+            .map_err(Into::into)
     }
 
-    pub async fn delete(dbpool: SqlitePool, id: i64) -> Result<(), Error> {
-        query("delete from todo where id = ?")
+    pub async fn delete(dbpool: AnyPool, owner_id: i64, id: i64) -> Result<(), Error> {
+        let sql = db::rewrite(&dbpool, "delete from todos where id = ? and owner_id = ?");
+        query(&sql)
             .bind(id)
+            .bind(owner_id)
             .execute(&dbpool)
             .await?;
-        Ok()
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+pub struct TodoFilter {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    completed: Option<bool>,
+}
+
+impl TodoFilter {
+    // A negative limit/offset means "no limit"/"no skip" on SQLite but
+    // errors on Postgres, so reject it as a bad request instead of quietly
+    // clamping it to a result the caller would mistake for "no todos".
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.limit.is_some_and(|limit| limit < 0) {
+            return Err(Error::BadRequest("limit must not be negative".to_string()));
+        }
+        if self.offset.is_some_and(|offset| offset < 0) {
+            return Err(Error::BadRequest("offset must not be negative".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn completed(&self) -> Option<bool> {
+        self.completed
     }
 }
 
@@ -0,0 +1,61 @@
+// We're deriving the Serialize trait from the serde crate, and sqlx::FromRow which allows us to get a User from a SQLx query.
+
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{query_as, AnyPool};
+
+use crate::{db, error::Error};
+
+#[derive(Serialize, Clone, sqlx::FromRow)]
+pub struct User {
+    id: i64,
+    username: String,
+    #[serde(skip_serializing)]
+    password_hash: String,
+    created_at: NaiveDateTime,
+}
+
+impl User {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub async fn signup(dbpool: AnyPool, username: &str, password: &str) -> Result<User, Error> {
+        let password_hash = hash(password, DEFAULT_COST)?;
+
+        let sql = db::rewrite(
+            &dbpool,
+            "insert into users (username, password_hash) values (?, ?) returning *",
+        );
+        query_as(&sql)
+            .bind(username)
+            .bind(password_hash)
+            .fetch_one(&dbpool)
+            .await
+            .map_err(|err| match &err {
+                sqlx::Error::Database(db_err)
+                    if db_err.kind() == sqlx::error::ErrorKind::UniqueViolation =>
+                {
+                    Error::UsernameTaken
+                }
+                _ => err.into(),
+            })
+    }
+
+    pub async fn login(dbpool: AnyPool, username: &str, password: &str) -> Result<User, Error> {
+        let sql = db::rewrite(&dbpool, "select * from users where username = ?");
+        let user: Option<User> = query_as(&sql)
+            .bind(username)
+            .fetch_optional(&dbpool)
+            .await?;
+
+        let user = user.ok_or(Error::Unauthorized)?;
+
+        if verify(password, &user.password_hash)? {
+            Ok(user)
+        } else {
+            Err(Error::Unauthorized)
+        }
+    }
+}